@@ -1,4 +1,8 @@
-use std::{io::Read, path::Path, process::Command};
+use std::{
+    path::Path,
+    process::Command,
+    time::{Duration, Instant},
+};
 
 const QEMU_ARGS: &[&str] = &[
     "-device",
@@ -9,13 +13,199 @@ const QEMU_ARGS: &[&str] = &[
     "none",
     "--no-reboot",
 ];
+
+/// QEMU arguments shared by the UEFI targets that cannot use the x86-only
+/// `isa-debug-exit` device. Success and failure are signalled by the serial
+/// sentinels below instead of an exit code.
+const QEMU_ARGS_NO_DEBUG_EXIT: &[&str] = &["-serial", "stdio", "-display", "none", "--no-reboot"];
+
+/// Serial line a kernel prints to report success on targets without
+/// `isa-debug-exit` (AArch64, RISC-V).
+const SUCCESS_SENTINEL: &str = "[test-runner] success";
+/// Serial line a kernel prints to report failure on targets without
+/// `isa-debug-exit`.
+const FAILURE_SENTINEL: &str = "[test-runner] failure";
+
 const SEPARATOR: &str = "\n____________________________________\n";
 
+/// Upper bound on a single QEMU run. A kernel that never reaches the exit
+/// device — a deadlock or a triple-fault loop — would otherwise block the test
+/// suite indefinitely, so the run is killed once this elapses. Overridable via
+/// the `BOOTLOADER_TEST_TIMEOUT` environment variable (whole seconds).
+const DEFAULT_TEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Resolves the per-run watchdog timeout, honouring `BOOTLOADER_TEST_TIMEOUT`.
+fn test_timeout() -> Duration {
+    match std::env::var("BOOTLOADER_TEST_TIMEOUT") {
+        Ok(value) => {
+            let secs = value
+                .parse()
+                .unwrap_or_else(|_| panic!("invalid BOOTLOADER_TEST_TIMEOUT `{value}`"));
+            Duration::from_secs(secs)
+        }
+        Err(_) => DEFAULT_TEST_TIMEOUT,
+    }
+}
+
+/// Target architecture a test kernel was built for. Selects the QEMU binary,
+/// machine/firmware flavour, and the pass/fail signalling convention.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Arch {
+    X86_64,
+    Aarch64,
+    Riscv64,
+}
+
+impl Arch {
+    fn qemu_binary(self) -> &'static str {
+        match self {
+            Arch::X86_64 => "qemu-system-x86_64",
+            Arch::Aarch64 => "qemu-system-aarch64",
+            Arch::Riscv64 => "qemu-system-riscv64",
+        }
+    }
+
+    /// Machine/CPU arguments required before the firmware and drive arguments.
+    fn machine_args(self) -> &'static [&'static str] {
+        match self {
+            Arch::X86_64 => &[],
+            Arch::Aarch64 => &["-machine", "virt", "-cpu", "cortex-a72"],
+            Arch::Riscv64 => &["-machine", "virt"],
+        }
+    }
+
+    /// Arguments controlling the exit convention and serial wiring.
+    fn exit_args(self) -> &'static [&'static str] {
+        match self {
+            Arch::X86_64 => QEMU_ARGS,
+            // `isa-debug-exit` is x86-specific; rely on the serial sentinels.
+            Arch::Aarch64 | Arch::Riscv64 => QEMU_ARGS_NO_DEBUG_EXIT,
+        }
+    }
+}
+
 pub fn run_test_kernel(kernel_binary_path: &str) {
-    run_test_kernel_with_ramdisk(kernel_binary_path, None)
+    run_test_kernel_impl(kernel_binary_path, None, arch_from_env())
+}
+
+/// Like [`run_test_kernel`] but boots the kernel on `arch`'s QEMU target,
+/// dispatching to `qemu-system-aarch64` / `qemu-system-riscv64` with the matching
+/// firmware. This mirrors the multi-target `build`/`run` dispatch other OS build
+/// tools expose. With the `bios` feature the BIOS disk image is still booted on
+/// x86-64, whatever `arch` is.
+///
+/// See [`run_test_kernel_on_uefi_arch`] for the current limitation: only
+/// [`Arch::X86_64`] is functional until an arch-specific image builder exists.
+pub fn run_test_kernel_for_arch(kernel_binary_path: &str, arch: Arch) {
+    run_test_kernel_impl(kernel_binary_path, None, arch)
+}
+
+/// Resolves the target architecture for the default entry points from
+/// `BOOTLOADER_TEST_ARCH` (`x86_64` | `aarch64` | `riscv64`), defaulting to
+/// x86-64 so existing callers keep booting on x86.
+fn arch_from_env() -> Arch {
+    match std::env::var("BOOTLOADER_TEST_ARCH").as_deref() {
+        Ok("aarch64") => Arch::Aarch64,
+        Ok("riscv64") => Arch::Riscv64,
+        Ok("x86_64") | Err(_) => Arch::X86_64,
+        Ok(other) => panic!("unknown BOOTLOADER_TEST_ARCH `{other}`"),
+    }
+}
+
+/// Boots a UEFI disk image built for `arch`. x86-64 images use the prebuilt
+/// OVMF `-bios`; AArch64 and RISC-V load their UEFI firmware through a pflash
+/// pair (read-only code + writable vars), matching how `qemu-system-aarch64`
+/// and `qemu-system-riscv64` expect `virt`-machine firmware to be supplied.
+///
+/// The firmware locations default to the usual distribution paths and can be
+/// overridden per arch with the `*_CODE` / `*_VARS` environment variables.
+///
+/// # Limitations
+///
+/// This selects the QEMU binary, machine and firmware per architecture, but the
+/// image itself must already be built for `arch`. [`bootloader::UefiBoot`] emits
+/// the x86-64 removable-media loader `\EFI\BOOT\BOOTX64.EFI`, whereas AArch64 and
+/// RISC-V firmware search for `\EFI\BOOT\BOOTAA64.EFI` / `\EFI\BOOT\BOOTRISCV64.EFI`.
+/// Until an arch-specific image builder exists, an image produced by `UefiBoot`
+/// carries no loader those firmwares will launch, so the non-x86 arms never
+/// reach the success sentinel. They are wired up here for when such a builder
+/// lands; today only [`Arch::X86_64`] is functional.
+#[cfg(feature = "uefi")]
+pub fn run_test_kernel_on_uefi_arch(out_gpt_path: &Path, arch: Arch) {
+    let drive = format!("format=raw,file={}", out_gpt_path.display());
+    match arch {
+        Arch::X86_64 => run_test_kernel_on_uefi(out_gpt_path),
+        Arch::Aarch64 => {
+            let (code, vars) = uefi_firmware_pflash(
+                "AAVMF_CODE",
+                "/usr/share/AAVMF/AAVMF_CODE.fd",
+                "AAVMF_VARS",
+                "/usr/share/AAVMF/AAVMF_VARS.fd",
+                out_gpt_path,
+            );
+            let args = [
+                "-drive",
+                &format!("if=pflash,format=raw,readonly=on,file={}", code.display()),
+                "-drive",
+                &format!("if=pflash,format=raw,file={}", vars.display()),
+                "-drive",
+                &format!("if=none,id=disk,{drive}"),
+                "-device",
+                "virtio-blk-device,drive=disk",
+            ];
+            run_qemu(arch, args);
+        }
+        Arch::Riscv64 => {
+            let (code, vars) = uefi_firmware_pflash(
+                "RISCV_UEFI_CODE",
+                "/usr/share/qemu/RISCV_VIRT_CODE.fd",
+                "RISCV_UEFI_VARS",
+                "/usr/share/qemu/RISCV_VIRT_VARS.fd",
+                out_gpt_path,
+            );
+            let args = [
+                "-drive",
+                &format!("if=pflash,format=raw,readonly=on,file={}", code.display()),
+                "-drive",
+                &format!("if=pflash,format=raw,file={}", vars.display()),
+                "-drive",
+                &format!("if=none,id=disk,{drive}"),
+                "-device",
+                "virtio-blk-device,drive=disk",
+            ];
+            run_qemu(arch, args);
+        }
+    }
+}
+
+/// Resolves a UEFI firmware pflash pair, honouring the given environment
+/// overrides and making a private writable copy of the variables store next to
+/// `image` so concurrent runs don't share it.
+#[cfg(feature = "uefi")]
+fn uefi_firmware_pflash(
+    code_env: &str,
+    code_default: &str,
+    vars_env: &str,
+    vars_default: &str,
+    image: &Path,
+) -> (std::path::PathBuf, std::path::PathBuf) {
+    let code = std::env::var_os(code_env)
+        .map(std::path::PathBuf::from)
+        .unwrap_or_else(|| code_default.into());
+    let vars_template = std::env::var_os(vars_env)
+        .map(std::path::PathBuf::from)
+        .unwrap_or_else(|| vars_default.into());
+
+    let vars_copy = image.with_extension("vars.fd");
+    std::fs::copy(&vars_template, &vars_copy).unwrap();
+    (code, vars_copy)
 }
 
 pub fn run_test_kernel_with_ramdisk(kernel_binary_path: &str, ramdisk_path: Option<&Path>) {
+    run_test_kernel_impl(kernel_binary_path, ramdisk_path, arch_from_env())
+}
+
+fn run_test_kernel_impl(kernel_binary_path: &str, ramdisk_path: Option<&Path>, arch: Arch) {
     let kernel_path = Path::new(kernel_binary_path);
 
     #[cfg(feature = "uefi")]
@@ -29,15 +219,26 @@ pub fn run_test_kernel_with_ramdisk(kernel_binary_path: &str, ramdisk_path: Opti
         }
         uefi_builder.create_disk_image(&gpt_path).unwrap();
 
-        // create a TFTP folder with the kernel executable and UEFI bootloader for
-        // UEFI PXE booting
-        let tftp_path = kernel_path.with_extension("tftp");
-        uefi_builder.create_pxe_tftp_folder(&tftp_path).unwrap();
+        // Boot the disk image on the requested architecture's QEMU target.
+        run_test_kernel_on_uefi_arch(&gpt_path, arch);
 
-        run_test_kernel_on_uefi(&gpt_path);
-        run_test_kernel_on_uefi_pxe(&tftp_path);
+        // The network-boot paths use the x86 `isa-debug-exit` convention, so
+        // only exercise them on x86-64.
+        if arch == Arch::X86_64 {
+            // create a TFTP folder with the kernel executable and UEFI
+            // bootloader, reused as the document root for both PXE/TFTP and
+            // HTTP network booting
+            let tftp_path = kernel_path.with_extension("tftp");
+            uefi_builder.create_pxe_tftp_folder(&tftp_path).unwrap();
+
+            run_test_kernel_on_uefi_pxe(&tftp_path);
+            run_test_kernel_on_uefi_http(&tftp_path);
+        }
     }
 
+    #[cfg(not(feature = "uefi"))]
+    let _ = arch;
+
     #[cfg(feature = "bios")]
     {
         // create an MBR disk image for legacy BIOS booting
@@ -53,6 +254,96 @@ pub fn run_test_kernel_with_ramdisk(kernel_binary_path: &str, ramdisk_path: Opti
     }
 }
 
+/// A single A/B boot slot: a kernel together with its ChromeOS-style selection
+/// attributes (priority, tries-remaining and the successful-boot flag).
+#[cfg(feature = "uefi")]
+pub struct BootSlot<'a> {
+    pub kernel: &'a Path,
+    /// Slot priority, 15 = highest, 0 = not bootable.
+    pub priority: Priority,
+    /// Remaining boot attempts before the slot is skipped.
+    pub tries: Tries,
+    /// Whether the slot has already booted successfully.
+    pub successful: bool,
+}
+
+#[cfg(feature = "uefi")]
+impl BootSlot<'_> {
+    /// A slot is bootable when it has a non-zero priority and either already
+    /// booted successfully or still has tries remaining.
+    fn bootable(&self) -> bool {
+        self.priority.0 > 0 && (self.successful || self.tries.0 > 0)
+    }
+}
+
+/// A boot-slot priority in the range `0..=15`; 15 is highest, 0 marks a slot as
+/// not bootable.
+#[cfg(feature = "uefi")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Priority(u8);
+
+/// The number of remaining boot attempts for a slot, in the range `0..=15`.
+#[cfg(feature = "uefi")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Tries(u8);
+
+#[cfg(feature = "uefi")]
+impl Priority {
+    /// Creates a priority, panicking if `value` does not fit the 4-bit field.
+    pub fn new(value: u8) -> Self {
+        assert!(value <= 15, "priority must be 0..=15, got {value}");
+        Self(value)
+    }
+}
+
+#[cfg(feature = "uefi")]
+impl Tries {
+    /// Creates a tries-remaining count, panicking if `value` does not fit the
+    /// 4-bit field.
+    pub fn new(value: u8) -> Self {
+        assert!(value <= 15, "tries must be 0..=15, got {value}");
+        Self(value)
+    }
+}
+
+/// Resolves the A/B slot the bootloader should boot and boots its kernel,
+/// asserting that kernel starts (exit code 33).
+///
+/// # Scope
+///
+/// This exercises the **host-side** [`select_boot_slot`] policy and then boots
+/// the winning slot's kernel on its own. It does *not* emit a two-partition
+/// image or drive a firmware/bootloader that reads the GPT attribute bits: the
+/// current [`bootloader::UefiBoot`] builds a single-kernel image, so there is no
+/// second partition to fall back to. A genuine firmware-side fallback test
+/// (install both kernels, exhaust one slot's tries and assert the other boots)
+/// needs an arch-specific multi-partition image builder that does not exist yet.
+#[cfg(feature = "uefi")]
+pub fn run_test_kernel_ab_slots(slot_a: BootSlot, slot_b: BootSlot, out_gpt_path: &Path) {
+    let selected = select_boot_slot(&slot_a, &slot_b).expect("no bootable slot");
+
+    bootloader::UefiBoot::new(selected.kernel)
+        .create_disk_image(out_gpt_path)
+        .unwrap();
+
+    run_test_kernel_on_uefi(out_gpt_path);
+}
+
+/// Returns the slot the bootloader should boot: the highest-priority bootable
+/// slot, preferring `slot_a` on a tie. `None` if neither slot is bootable.
+#[cfg(feature = "uefi")]
+fn select_boot_slot<'s, 'a>(
+    slot_a: &'s BootSlot<'a>,
+    slot_b: &'s BootSlot<'a>,
+) -> Option<&'s BootSlot<'a>> {
+    match (slot_a.bootable(), slot_b.bootable()) {
+        (true, true) if slot_b.priority.0 > slot_a.priority.0 => Some(slot_b),
+        (true, _) => Some(slot_a),
+        (false, true) => Some(slot_b),
+        (false, false) => None,
+    }
+}
+
 #[cfg(feature = "uefi")]
 pub fn run_test_kernel_on_uefi(out_gpt_path: &Path) {
     let ovmf_pure_efi = ovmf_prebuilt::ovmf_pure_efi();
@@ -62,7 +353,269 @@ pub fn run_test_kernel_on_uefi(out_gpt_path: &Path) {
         "-drive",
         &format!("format=raw,file={}", out_gpt_path.display()),
     ];
-    run_qemu(args);
+    run_qemu(Arch::X86_64, args);
+}
+
+/// Owner GUID recorded for the Secure Boot variables enrolled from a generated
+/// keypair.
+#[cfg(feature = "uefi")]
+const SECURE_BOOT_GUID: &str = "11111111-2222-3333-4444-555555555555";
+
+/// A generated Secure Boot keypair set: a Platform Key (PK), a Key Exchange Key
+/// (KEK) and a signature-database (`db`) entry.
+///
+/// The `db` key signs the bootloader image; all three certificates are enrolled
+/// into the OVMF variables store so the firmware trusts that signature. The keys
+/// are self-signed X.509 certificates generated with `openssl` into a private
+/// directory that is removed when the value is dropped.
+#[cfg(feature = "uefi")]
+pub struct SecureBootKeys {
+    dir: std::path::PathBuf,
+}
+
+#[cfg(feature = "uefi")]
+impl SecureBootKeys {
+    /// Generates a fresh PK/KEK/db triple with `openssl`.
+    pub fn generate() -> std::io::Result<Self> {
+        static NEXT_ID: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+        let id = NEXT_ID.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        let dir = std::env::temp_dir().join(format!(
+            "bootloader-secboot-{}-{id}",
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&dir)?;
+        for name in ["PK", "KEK", "db"] {
+            run_tool(
+                "openssl",
+                &[
+                    "req",
+                    "-x509",
+                    "-newkey",
+                    "rsa:2048",
+                    "-nodes",
+                    "-sha256",
+                    "-days",
+                    "3650",
+                    "-subj",
+                    &format!("/CN=bootloader-test {name}/"),
+                    "-keyout",
+                    dir.join(format!("{name}.key")).to_str().unwrap(),
+                    "-out",
+                    dir.join(format!("{name}.crt")).to_str().unwrap(),
+                ],
+            )?;
+        }
+        Ok(Self { dir })
+    }
+
+    fn key(&self, name: &str) -> std::path::PathBuf {
+        self.dir.join(format!("{name}.key"))
+    }
+
+    fn cert(&self, name: &str) -> std::path::PathBuf {
+        self.dir.join(format!("{name}.crt"))
+    }
+
+    /// Authenticode-signs `\EFI\BOOT\BOOTX64.EFI` inside the GPT image at
+    /// `gpt_path` with the `db` key, so a Secure Boot firmware with this key
+    /// enrolled accepts it.
+    ///
+    /// The bootloader lives on the FAT EFI System Partition, so it is extracted
+    /// with `mtools` at the partition's byte offset, signed with `sbsign`
+    /// (which appends a PKCS#7 signature in the PE security directory), and
+    /// written back.
+    pub fn sign_uefi_image(&self, gpt_path: &Path) -> std::io::Result<()> {
+        let image = format!("{}@@{}", gpt_path.display(), esp_byte_offset(gpt_path)?);
+        let unsigned = self.dir.join("BOOTX64.EFI");
+        let signed = self.dir.join("BOOTX64.signed.efi");
+
+        run_tool(
+            "mcopy",
+            &[
+                "-n",
+                "-i",
+                &image,
+                "::/EFI/BOOT/BOOTX64.EFI",
+                unsigned.to_str().unwrap(),
+            ],
+        )?;
+        run_tool(
+            "sbsign",
+            &[
+                "--key",
+                self.key("db").to_str().unwrap(),
+                "--cert",
+                self.cert("db").to_str().unwrap(),
+                "--output",
+                signed.to_str().unwrap(),
+                unsigned.to_str().unwrap(),
+            ],
+        )?;
+        run_tool(
+            "mcopy",
+            &[
+                "-o",
+                "-i",
+                &image,
+                signed.to_str().unwrap(),
+                "::/EFI/BOOT/BOOTX64.EFI",
+            ],
+        )
+    }
+
+    /// Builds a writable OVMF variables store with `PK`/`KEK`/`db` pre-enrolled
+    /// from these keys, using `virt-fw-vars` over a template store. The template
+    /// defaults to the distribution `OVMF_VARS` and can be overridden with
+    /// `OVMF_VARS_SECBOOT`.
+    fn enrolled_vars_store(&self) -> std::io::Result<std::path::PathBuf> {
+        let template = std::env::var_os("OVMF_VARS_SECBOOT")
+            .map(std::path::PathBuf::from)
+            .unwrap_or_else(|| "/usr/share/OVMF/OVMF_VARS.fd".into());
+        let out = self.dir.join("OVMF_VARS.enrolled.fd");
+        run_tool(
+            "virt-fw-vars",
+            &[
+                "--input",
+                template.to_str().unwrap(),
+                "--output",
+                out.to_str().unwrap(),
+                "--set-pk",
+                SECURE_BOOT_GUID,
+                self.cert("PK").to_str().unwrap(),
+                "--add-kek",
+                SECURE_BOOT_GUID,
+                self.cert("KEK").to_str().unwrap(),
+                "--add-db",
+                SECURE_BOOT_GUID,
+                self.cert("db").to_str().unwrap(),
+                "--secure-boot",
+            ],
+        )?;
+        Ok(out)
+    }
+}
+
+#[cfg(feature = "uefi")]
+impl Drop for SecureBootKeys {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// Runs an external tool, mapping a non-zero exit into an `io::Error`.
+#[cfg(feature = "uefi")]
+fn run_tool(program: &str, args: &[&str]) -> std::io::Result<()> {
+    let status = Command::new(program).args(args).status()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(std::io::Error::other(format!(
+            "`{program}` exited with {status}"
+        )))
+    }
+}
+
+/// Reads the byte offset of the first (EFI System) partition from a GPT image,
+/// parsing the primary GPT header and its first partition entry directly.
+#[cfg(feature = "uefi")]
+fn esp_byte_offset(gpt_path: &Path) -> std::io::Result<u64> {
+    use std::io::{Read, Seek, SeekFrom};
+
+    const BLOCK: u64 = 512;
+    let mut file = std::fs::File::open(gpt_path)?;
+    let mut buf = [0u8; 8];
+
+    // The GPT header lives in LBA 1; the partition entry array starting LBA is
+    // stored at offset 72.
+    file.seek(SeekFrom::Start(BLOCK + 72))?;
+    file.read_exact(&mut buf)?;
+    let entries_lba = u64::from_le_bytes(buf);
+
+    // The first partition entry's starting LBA is at offset 32 within it.
+    file.seek(SeekFrom::Start(entries_lba * BLOCK + 32))?;
+    file.read_exact(&mut buf)?;
+    let first_lba = u64::from_le_bytes(buf);
+
+    Ok(first_lba * BLOCK)
+}
+
+/// Paths to a Secure Boot firmware split whose `db`/`KEK`/`PK` variables have
+/// been pre-enrolled with the keypair used to sign the bootloader image.
+///
+/// The OVMF Secure Boot build keeps the read-only code and the writable NVRAM
+/// store in two separate pflash files, so both have to be handed to QEMU. Each
+/// [`SecureBootFirmware::enrolled`] call writes a fresh variables store into the
+/// signer's private directory; runs that share one `SecureBootFirmware` also
+/// share its store.
+#[cfg(feature = "uefi")]
+pub struct SecureBootFirmware {
+    code: std::path::PathBuf,
+    vars: std::path::PathBuf,
+}
+
+#[cfg(feature = "uefi")]
+impl SecureBootFirmware {
+    /// Resolves the Secure Boot OVMF split, enrolling the `db` certificate
+    /// matching `signer` into a private copy of the variables store.
+    ///
+    /// The firmware locations default to the usual distribution paths and can
+    /// be overridden with `OVMF_CODE_SECBOOT` / `OVMF_VARS_SECBOOT`.
+    pub fn enrolled(signer: &SecureBootKeys) -> std::io::Result<Self> {
+        let code = std::env::var_os("OVMF_CODE_SECBOOT")
+            .map(std::path::PathBuf::from)
+            .unwrap_or_else(|| "/usr/share/OVMF/OVMF_CODE.secboot.fd".into());
+
+        // Derive an enrolled NVRAM store from the signer's PK/KEK/db certs and
+        // place the writable copy in the signer's private key directory.
+        let vars = signer.enrolled_vars_store()?;
+        Ok(Self { code, vars })
+    }
+}
+
+/// Boots a Secure Boot–signed image against the enrolled OVMF split and asserts
+/// the firmware accepts the signature (the kernel still signals success via the
+/// isa-debug-exit device, exit code 33).
+///
+/// Sign the image with [`SecureBootKeys::sign_uefi_image`] first so that
+/// `\EFI\BOOT\BOOTX64.EFI` carries an Authenticode signature chaining to the
+/// `db` key enrolled in `firmware`.
+#[cfg(feature = "uefi")]
+pub fn run_test_kernel_on_uefi_secure_boot(out_gpt_path: &Path, firmware: &SecureBootFirmware) {
+    let args = [
+        "-drive",
+        &format!(
+            "if=pflash,format=raw,readonly=on,file={}",
+            firmware.code.display()
+        ),
+        "-drive",
+        &format!("if=pflash,format=raw,file={}", firmware.vars.display()),
+        "-drive",
+        &format!("format=raw,file={}", out_gpt_path.display()),
+    ];
+    run_qemu(Arch::X86_64, args);
+}
+
+/// Like [`run_test_kernel_on_uefi_secure_boot`], but asserts the firmware
+/// *rejects* the image (unsigned or signed with a key absent from `db`). A
+/// rejected image never reaches the kernel, so the success exit code must not
+/// be produced.
+#[cfg(feature = "uefi")]
+pub fn run_test_kernel_on_uefi_secure_boot_rejected(
+    out_gpt_path: &Path,
+    firmware: &SecureBootFirmware,
+) {
+    let args = [
+        "-drive",
+        &format!(
+            "if=pflash,format=raw,readonly=on,file={}",
+            firmware.code.display()
+        ),
+        "-drive",
+        &format!("if=pflash,format=raw,file={}", firmware.vars.display()),
+        "-drive",
+        &format!("format=raw,file={}", out_gpt_path.display()),
+    ];
+    run_qemu_expect_rejection(args);
 }
 
 #[cfg(feature = "bios")]
@@ -71,7 +624,7 @@ pub fn run_test_kernel_on_bios(out_mbr_path: &Path) {
         "-drive",
         &(format!("format=raw,file={}", out_mbr_path.display())),
     ];
-    run_qemu(args);
+    run_qemu(Arch::X86_64, args);
 }
 
 #[cfg(feature = "uefi")]
@@ -88,18 +641,270 @@ pub fn run_test_kernel_on_uefi_pxe(out_tftp_path: &Path) {
         "-bios",
         ovmf_pure_efi.to_str().unwrap(),
     ];
-    run_qemu(args);
+    run_qemu(Arch::X86_64, args);
+}
+
+/// An ordered expectation checked against a kernel's serial transcript by
+/// [`run_test_kernel_expect`].
+///
+/// Expectations must be satisfied in the order they are listed: each match may
+/// only consume output produced after the previous expectation matched, so a
+/// test can assert that one line is printed strictly before another.
+///
+/// Matching is substring-only. Regex matching is intentionally out of scope:
+/// the runner does not depend on a regex engine, and ordered substring
+/// assertions already cover the before/after checks these tests need.
+pub enum Expect<'a> {
+    /// The transcript must contain this substring.
+    Substring(&'a str),
+}
+
+/// Boots `kernel_binary_path` and asserts that its serial output satisfies
+/// `expectations` in order, instead of relying solely on the isa-debug-exit
+/// pass/fail code.
+///
+/// The captured transcript is returned so behavioural integration tests can do
+/// further matching; on a missing or out-of-order expectation the whole
+/// transcript is included in the panic message as a diff-style report.
+#[cfg(feature = "uefi")]
+pub fn run_test_kernel_expect(kernel_binary_path: &str, expectations: &[Expect]) -> String {
+    let kernel_path = Path::new(kernel_binary_path);
+    let gpt_path = kernel_path.with_extension("gpt");
+    bootloader::UefiBoot::new(kernel_path)
+        .create_disk_image(&gpt_path)
+        .unwrap();
+
+    let ovmf_pure_efi = ovmf_prebuilt::ovmf_pure_efi();
+    let args = [
+        "-bios",
+        ovmf_pure_efi.to_str().unwrap(),
+        "-drive",
+        &format!("format=raw,file={}", gpt_path.display()),
+    ];
+    let run = spawn_qemu(Arch::X86_64, args);
+    if run.status.is_none() {
+        timed_out(&run.serial);
+    }
+
+    if let Err(report) = check_expectations(&run.serial, expectations) {
+        panic!("{report}");
+    }
+    run.serial
+}
+
+/// Scans `serial` for `expectations` in order, returning a diff-style report as
+/// the `Err` value when one is missing or appears out of order.
+#[cfg(feature = "uefi")]
+fn check_expectations(serial: &str, expectations: &[Expect]) -> Result<(), String> {
+    let mut cursor = 0;
+    for (index, expect) in expectations.iter().enumerate() {
+        let remaining = &serial[cursor..];
+        let matched = match expect {
+            Expect::Substring(needle) => remaining.find(needle).map(|start| start + needle.len()),
+        };
+
+        match matched {
+            Some(end) => cursor += end,
+            None => {
+                let description = match expect {
+                    Expect::Substring(needle) => format!("substring `{needle}`"),
+                };
+                return Err(format!(
+                    "serial expectation #{} ({description}) not found after the preceding \
+                     matches\n--- captured serial ---\n{serial}\n-----------------------",
+                    index + 1,
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Boots the bootloader and kernel over UEFI HTTP Boot instead of TFTP.
+///
+/// The HTTP boot client fetches the same `bootloader` + kernel artifacts the
+/// PXE path serves, so `out_http_path` is the TFTP folder produced by
+/// [`bootloader::UefiBoot::create_pxe_tftp_folder`]. A minimal HTTP server is
+/// spawned on the host to serve that directory, and OVMF's HTTP Boot client is
+/// pointed at it through QEMU's user-mode `bootfile=http://…` option; inside the
+/// guest the host is reachable at the fixed `10.0.2.2` SLIRP address. The server
+/// is torn down when the run returns.
+#[cfg(feature = "uefi")]
+pub fn run_test_kernel_on_uefi_http(out_http_path: &Path) {
+    let server = HttpBootServer::serve(out_http_path).unwrap();
+    let ovmf_pure_efi = ovmf_prebuilt::ovmf_pure_efi();
+    let args = [
+        "-netdev",
+        &format!(
+            "user,id=net0,bootfile=http://10.0.2.2:{}/bootloader",
+            server.port()
+        ),
+        "-device",
+        "virtio-net-pci,netdev=net0",
+        "-bios",
+        ovmf_pure_efi.to_str().unwrap(),
+    ];
+    run_qemu(Arch::X86_64, args);
+}
+
+/// A minimal blocking HTTP/1.0 file server backing the UEFI HTTP Boot test.
+///
+/// It answers `GET` requests with the matching file under the served root and
+/// shuts its accept loop down when dropped, so the serving thread never
+/// outlives the QEMU run.
+#[cfg(feature = "uefi")]
+struct HttpBootServer {
+    port: u16,
+    shutdown: std::sync::Arc<std::sync::atomic::AtomicBool>,
+    handle: Option<std::thread::JoinHandle<()>>,
+}
+
+#[cfg(feature = "uefi")]
+impl HttpBootServer {
+    fn serve(root: &Path) -> std::io::Result<Self> {
+        use std::sync::atomic::{AtomicBool, Ordering};
+
+        let listener = std::net::TcpListener::bind(("127.0.0.1", 0))?;
+        listener.set_nonblocking(true)?;
+        let port = listener.local_addr()?.port();
+
+        let root = root.to_path_buf();
+        let shutdown = std::sync::Arc::new(AtomicBool::new(false));
+        let handle = {
+            let shutdown = shutdown.clone();
+            std::thread::spawn(move || {
+                while !shutdown.load(Ordering::Relaxed) {
+                    match listener.accept() {
+                        Ok((stream, _)) => {
+                            let _ = serve_http_request(stream, &root);
+                        }
+                        Err(ref e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                            std::thread::sleep(Duration::from_millis(20));
+                        }
+                        Err(_) => break,
+                    }
+                }
+            })
+        };
+
+        Ok(Self {
+            port,
+            shutdown,
+            handle: Some(handle),
+        })
+    }
+
+    fn port(&self) -> u16 {
+        self.port
+    }
 }
 
-fn run_qemu<'a, A>(args: A)
+#[cfg(feature = "uefi")]
+impl Drop for HttpBootServer {
+    fn drop(&mut self) {
+        self.shutdown
+            .store(true, std::sync::atomic::Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Answers a single HTTP Boot request by streaming back the requested file
+/// relative to `root`, replying `404` when it is missing.
+#[cfg(feature = "uefi")]
+fn serve_http_request(mut stream: std::net::TcpStream, root: &Path) -> std::io::Result<()> {
+    use std::io::{BufRead, Write};
+
+    let mut reader = std::io::BufReader::new(stream.try_clone()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // Drain the remaining headers up to the blank line.
+    let mut header = String::new();
+    loop {
+        header.clear();
+        let read = reader.read_line(&mut header)?;
+        if read == 0 || header == "\r\n" || header == "\n" {
+            break;
+        }
+    }
+
+    // Request line looks like `GET /bootloader HTTP/1.1`.
+    let path = request_line.split_whitespace().nth(1).unwrap_or("/");
+    let file_path = root.join(path.trim_start_matches('/'));
+
+    match std::fs::read(&file_path) {
+        Ok(body) => {
+            write!(
+                stream,
+                "HTTP/1.0 200 OK\r\nContent-Length: {}\r\n\
+                 Content-Type: application/octet-stream\r\n\r\n",
+                body.len(),
+            )?;
+            stream.write_all(&body)?;
+        }
+        Err(_) => write!(stream, "HTTP/1.0 404 Not Found\r\nContent-Length: 0\r\n\r\n")?,
+    }
+    stream.flush()
+}
+
+fn run_qemu<'a, A>(arch: Arch, args: A)
+where
+    A: IntoIterator<Item = &'a str>,
+{
+    let run = spawn_qemu(arch, args);
+    let Some(exit_status) = run.status else {
+        timed_out(&run.serial);
+    };
+
+    match arch {
+        Arch::X86_64 => match exit_status.code() {
+            Some(33) => {}                     // success
+            Some(35) => panic!("Test failed"), // failure
+            other => panic!("Test failed with unexpected exit code `{other:?}`"),
+        },
+        Arch::Aarch64 | Arch::Riscv64 => {
+            if run.serial.contains(FAILURE_SENTINEL) {
+                panic!("Test failed (serial reported `{FAILURE_SENTINEL}`)");
+            }
+            if !run.serial.contains(SUCCESS_SENTINEL) {
+                panic!("Test did not report success (no `{SUCCESS_SENTINEL}` on serial)");
+            }
+        }
+    }
+}
+
+/// The result of a single QEMU run: its exit status (or `None` when the
+/// watchdog killed it before it exited on its own) and the captured serial
+/// transcript.
+struct QemuRun {
+    status: Option<std::process::ExitStatus>,
+    serial: String,
+}
+
+/// Panics with the standard "timed out" report, quoting the transcript gathered
+/// so far.
+fn timed_out(serial: &str) -> ! {
+    panic!(
+        "test timed out after {}s\n--- captured serial ---\n{serial}\n-----------------------",
+        test_timeout().as_secs(),
+    );
+}
+
+/// Spawns QEMU, streaming stdout/stderr through to the parent while teeing
+/// stdout into an in-memory buffer. Returns once the child exits or the
+/// watchdog fires, yielding the exit status (if any) together with the captured
+/// serial transcript.
+fn spawn_qemu<'a, A>(arch: Arch, args: A) -> QemuRun
 where
     A: IntoIterator<Item = &'a str>,
 {
     use std::process::Stdio;
 
-    let mut run_cmd = Command::new("qemu-system-x86_64");
+    let mut run_cmd = Command::new(arch.qemu_binary());
+    run_cmd.args(arch.machine_args());
     run_cmd.args(args);
-    run_cmd.args(QEMU_ARGS);
+    run_cmd.args(arch.exit_args());
     let run_cmd_str = format!("{run_cmd:?}");
 
     run_cmd.stdout(Stdio::piped());
@@ -107,17 +912,31 @@ where
 
     let mut child = run_cmd.spawn().unwrap();
 
-    let child_stdout = child.stdout.take().unwrap();
+    let mut child_stdout = child.stdout.take().unwrap();
     let mut child_stderr = child.stderr.take().unwrap();
 
-    let copy_stdout = std::thread::spawn(move || {
-        let print_cmd = format!("\nRunning {run_cmd_str}\n\n").into_bytes();
-        let mut output = print_cmd.chain(child_stdout).chain(SEPARATOR.as_bytes());
-        std::io::copy(
-            &mut output,
-            &mut strip_ansi_escapes::Writer::new(std::io::stdout()),
-        )
-    });
+    // Capture the serial transcript for the sentinel check on non-x86 targets
+    // and for expectation matching. ANSI escapes are stripped before both
+    // display and capture, and the injected command banner and trailing
+    // separator are written straight to stdout so they stay out of the
+    // transcript — the capture holds exactly the serial bytes the user sees.
+    let capture = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let copy_stdout = {
+        let capture = capture.clone();
+        std::thread::spawn(move || -> std::io::Result<()> {
+            use std::io::Write;
+
+            write!(std::io::stdout(), "\nRunning {run_cmd_str}\n\n")?;
+            let mut sink = strip_ansi_escapes::Writer::new(Tee {
+                inner: std::io::stdout(),
+                buffer: capture,
+            });
+            std::io::copy(&mut child_stdout, &mut sink)?;
+            sink.flush()?;
+            write!(std::io::stdout(), "{SEPARATOR}")?;
+            Ok(())
+        })
+    };
     let copy_stderr = std::thread::spawn(move || {
         std::io::copy(
             &mut child_stderr,
@@ -125,13 +944,63 @@ where
         )
     });
 
-    let exit_status = child.wait().unwrap();
-    match exit_status.code() {
-        Some(33) => {}                     // success
-        Some(35) => panic!("Test failed"), // success
-        other => panic!("Test failed with unexpected exit code `{other:?}`"),
-    }
+    // Poll for exit so a hung kernel can be killed once the watchdog fires,
+    // rather than blocking forever in `wait()`. A watchdog kill yields a `None`
+    // status so callers can distinguish it from a clean exit.
+    let timeout = test_timeout();
+    let start = Instant::now();
+    let status = loop {
+        if let Some(status) = child.try_wait().unwrap() {
+            break Some(status);
+        }
+        if start.elapsed() >= timeout {
+            child.kill().ok();
+            child.wait().ok();
+            break None;
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    };
 
+    // Drain the copy threads so the captured transcript is complete.
     copy_stdout.join().unwrap().unwrap();
     copy_stderr.join().unwrap().unwrap();
+
+    let serial = String::from_utf8_lossy(&capture.lock().unwrap()).into_owned();
+    QemuRun { status, serial }
+}
+
+/// A writer that forwards everything to `inner` while also collecting it into a
+/// shared buffer, used to scan the serial stream for sentinel lines.
+struct Tee<W> {
+    inner: W,
+    buffer: std::sync::Arc<std::sync::Mutex<Vec<u8>>>,
+}
+
+impl<W: std::io::Write> std::io::Write for Tee<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.buffer.lock().unwrap().extend_from_slice(buf);
+        self.inner.write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Runs QEMU like [`run_qemu`] but asserts the image is *not* accepted.
+///
+/// A Secure Boot rejection never hands control to the kernel, so the
+/// `isa-debug-exit` success code (33) never fires: the firmware either drops to
+/// its boot menu (and the watchdog eventually kills QEMU, yielding no status) or
+/// exits with some other code. Either outcome counts as the expected rejection;
+/// only the success code means the image was wrongly accepted.
+#[cfg(feature = "uefi")]
+fn run_qemu_expect_rejection<'a, A>(args: A)
+where
+    A: IntoIterator<Item = &'a str>,
+{
+    let run = spawn_qemu(Arch::X86_64, args);
+    if run.status.and_then(|status| status.code()) == Some(33) {
+        panic!("Secure Boot image was accepted but should have been rejected");
+    }
 }